@@ -4,172 +4,509 @@
 //!
 //! This module exposes utilities to create and inspect `SubotaiHash` structures. A
 //! useful method is `sha1`, which allows you to create a sha-1 hash from some data,
-//! which can then be used as a key for a storage entry.
-use rand::{thread_rng, Rng};
+//! which can then be used as a key for a storage entry. The fixed-size hash type is
+//! generated by the `impl_subotai_hash!` macro over a byte length, so a wider
+//! `SubotaiHash256` is available for networks that address content by a 256-bit key.
+use rand::{Rng, OsRng, StdRng, SeedableRng};
 use itertools;
 use serde::{Serialize, Deserialize};
-use std::ops::BitXor;
+use std::ops::{BitXor, BitAnd, BitOr, Not, Index, IndexMut, Deref, DerefMut};
+use std::collections::HashMap;
+use std::error::Error;
 use std::fmt;
 use std::fmt::Write;
+use std::hash::{Hash, Hasher, BuildHasherDefault};
+use std::str::FromStr;
 use std::cmp::{PartialOrd, Ordering};
 use sha1;
 
 pub const HASH_SIZE : usize = 160;
 pub const HASH_SIZE_BYTES : usize = HASH_SIZE / 8;
 
-/// Subotai hash, a light wrapper over a li  ttle endian `HASH_SIZE` bit hash.
-/// It can be generated randomly or via sha-1 of a given string.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct SubotaiHash {
-   pub raw : [u8; HASH_SIZE_BYTES],
-}
+/// Generates a fixed-size Subotai hash type over a const byte length, sharing the
+/// `blank`/`random`/`height`/`flip_bit`/iterator/ordering/XOR logic across every width.
+macro_rules! impl_subotai_hash {
+   ($name:ident, $bytes:expr) => {
+      /// Subotai hash, a light wrapper over a little endian byte array.
+      /// It can be generated randomly; only `SubotaiHash160` also has digest constructors
+      /// (SHA-1, via `sha1` and `from_bytes`), and `SubotaiHash256` has none.
+      #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+      pub struct $name {
+         pub raw : [u8; $bytes],
+      }
 
-impl SubotaiHash {
-   /// Generates a blank hash (every bit set to 0).
-   pub fn blank() -> SubotaiHash {
-      SubotaiHash { raw : [0; HASH_SIZE_BYTES] }
-   }
+      impl $name {
+         /// Generates a blank hash (every bit set to 0).
+         pub fn blank() -> $name {
+            $name { raw : [0; $bytes] }
+         }
 
-   /// Generates a random hash via kernel supplied entropy.
-   pub fn random() -> SubotaiHash {
-      let mut hash = SubotaiHash::blank();
-//      thread_rng().fill_bytes(&mut hash.raw);
-      hash
+         /// Fills every byte of a fresh hash from the supplied RNG. This is the core
+         /// generator the other random constructors build upon, so a fixed seed produces
+         /// a reproducible stream.
+         pub fn random_with<R: Rng>(rng: &mut R) -> $name {
+            let mut hash = $name::blank();
+            rng.fill_bytes(&mut hash.raw);
+            hash
+         }
+
+         /// Generates a random hash via OS supplied entropy, for real node IDs.
+         pub fn random() -> $name {
+            let mut rng = OsRng::new().expect("could not open the OS random number generator");
+            $name::random_with(&mut rng)
+         }
+
+         /// Generates a random hash from a seeded, reproducible RNG, so test harnesses can
+         /// replay exact topologies.
+         pub fn random_with_seed(seed: usize) -> $name {
+            let mut rng = StdRng::from_seed(&[seed]);
+            $name::random_with(&mut rng)
+         }
+
+         /// Creates a random hash at a given XOR distance from another (height of their XOR value).
+         pub fn random_at_distance(reference: &$name, distance: usize) -> $name {
+            let mut rng = OsRng::new().expect("could not open the OS random number generator");
+            $name::random_at_distance_with(&mut rng, reference, distance)
+         }
+
+         /// Like `random_at_distance`, but drawing from a caller-provided RNG so the result
+         /// is deterministic under a fixed seed.
+         pub fn random_at_distance_with<R: Rng>(rng: &mut R, reference: &$name, distance: usize) -> $name {
+            let mut random_hash = $name::random_with(rng);
+            let distance_ones = (&random_hash ^ reference).into_ones();
+            for index in distance_ones.rev() {
+               random_hash.flip_bit(index);
+               match (&random_hash ^ reference).height() {
+                  Some(height) if height == distance => return random_hash,
+                  Some(height) if height > distance => (),
+                  _ => {
+                     random_hash.flip_bit(distance);
+                     return random_hash;
+                  }
+               }
+            }
+
+            random_hash.flip_bit(distance);
+            random_hash
+         }
+
+         /// Provides an iterator through the indices
+         /// of each of its "0" bits.
+         pub fn zeroes(&self) -> Zeroes {
+            Zeroes {
+               raw   : &self.raw,
+               index : 0,
+               rev   : $bytes * 8
+            }
+         }
+
+         /// Provides an iterator through the indices
+         /// of each of its "1" bits.
+         pub fn ones(&self) -> Ones {
+            Ones {
+               raw   : &self.raw,
+               index : 0,
+               rev   : $bytes * 8
+            }
+         }
+
+         /// Provides a consuming iterator through the
+         /// indices of each of its "0" bits.
+         pub fn into_zeroes(self) -> IntoZeroes {
+            IntoZeroes {
+               raw   : self.raw.to_vec(),
+               index : 0,
+               rev   : $bytes * 8
+            }
+         }
+
+         /// Provides a consuming iterator through the
+         /// indices of each of its "1" bits.
+         pub fn into_ones(self) -> IntoOnes {
+            IntoOnes {
+               raw   : self.raw.to_vec(),
+               index : 0,
+               rev   : $bytes * 8
+            }
+         }
+
+         /// Computes the bit index of the highest "1". Returns None for a blank hash.
+         pub fn height(&self) -> Option<usize> {
+            let last_nonzero_byte = self.raw.iter().enumerate().rev().find(|&pair| *pair.1 != 0);
+            if let Some((index, byte)) = last_nonzero_byte {
+               for bit in (0..8).rev() {
+                  if (byte & (1 << bit)) != 0 {
+                     return Some((8 * index + bit) as usize)
+                  }
+               }
+            }
+            None
+         }
+
+         /// Flips a bit in the hash.
+         pub fn flip_bit(&mut self, position : usize) {
+            if position >= $bytes * 8 { return; }
+            let byte = &mut self.raw[position / 8];
+            *byte ^= 1 << (position % 8);
+         }
+
+         /// Reconstructs a hash from the big-endian, `0x`-prefixed hex form emitted by its
+         /// `Display` implementation. Leading zeroes may be omitted, since `Display`
+         /// left-trims them, so `hash.to_string().parse().unwrap() == hash` holds for every
+         /// value.
+         pub fn from_hex(source: &str) -> Result<$name, ParseHashError> {
+            let trimmed = source.trim();
+            let body = if trimmed.starts_with("0x[") && trimmed.ends_with(']') {
+               &trimmed[3 .. trimmed.len() - 1]
+            } else if trimmed.starts_with("0x") {
+               &trimmed[2 ..]
+            } else {
+               trimmed
+            };
+
+            if body.len() > $bytes * 2 {
+               return Err(ParseHashError::TooLong { max_nibbles: $bytes * 2 });
+            }
+
+            // Walk the nibbles from least significant (tail of the string) towards the most
+            // significant, packing them into the little endian backing array.
+            let mut hash = $name::blank();
+            for (nibble, digit) in body.chars().rev().enumerate() {
+               let value = match digit.to_digit(16) {
+                  Some(value) => value as u8,
+                  None => return Err(ParseHashError::InvalidDigit(digit)),
+               };
+               hash.raw[nibble / 2] |= value << (4 * (nibble % 2));
+            }
+            Ok(hash)
+         }
+      }
+
+      impl FromStr for $name {
+         type Err = ParseHashError;
+
+         fn from_str(source: &str) -> Result<$name, ParseHashError> {
+            $name::from_hex(source)
+         }
+      }
+
+      impl fmt::Display for $name {
+         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let mut leftpad_over = false;
+            let mut hex = String::new();
+            hex.push_str("0x[");
+            for byte in self.raw.iter().rev() {
+               if *byte > 0u8 {
+                  leftpad_over = true;
+               }
+
+               if leftpad_over {
+                  write!(&mut hex, "{:01$X}", byte, 2).unwrap();
+               }
+            }
+            hex.push_str("]");
+            write!(f, "{}", hex)
+         }
+      }
+
+      impl PartialOrd for $name {
+         fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            for (a,b) in self.raw.iter().rev().zip(other.raw.iter().rev()) {
+               match a.cmp(b) {
+                  Ordering::Less => return Some(Ordering::Less),
+                  Ordering::Greater => return Some(Ordering::Greater),
+                  Ordering::Equal => ()
+               }
+            }
+            Some(Ordering::Equal)
+         }
+      }
+
+      impl Ord for $name {
+         fn cmp(&self, other: &Self) -> Ordering {
+            match self.partial_cmp(other) {
+               Some(order) => order,
+               None => Ordering::Equal
+            }
+         }
+      }
+
+      impl<'a, 'b> BitXor<&'b $name> for &'a $name {
+         type Output = $name;
+
+         fn bitxor (self, rhs: &'b $name) -> $name {
+            let mut result = $name::blank();
+            for (d, a, b) in itertools::multizip((&mut result.raw, &self.raw, &rhs.raw)) {
+               *d = a^b;
+            }
+            result
+         }
+      }
+
+      impl BitXor for $name {
+         type Output = $name;
+
+         fn bitxor (mut self, rhs: Self) -> $name {
+            for (a, b) in self.raw.iter_mut().zip(rhs.raw.iter()) {
+               *a ^= *b;
+            }
+            self
+         }
+      }
+
+      impl<'a, 'b> BitAnd<&'b $name> for &'a $name {
+         type Output = $name;
+
+         fn bitand (self, rhs: &'b $name) -> $name {
+            let mut result = $name::blank();
+            for (d, a, b) in itertools::multizip((&mut result.raw, &self.raw, &rhs.raw)) {
+               *d = a&b;
+            }
+            result
+         }
+      }
+
+      impl BitAnd for $name {
+         type Output = $name;
+
+         fn bitand (mut self, rhs: Self) -> $name {
+            for (a, b) in self.raw.iter_mut().zip(rhs.raw.iter()) {
+               *a &= *b;
+            }
+            self
+         }
+      }
+
+      impl<'a, 'b> BitOr<&'b $name> for &'a $name {
+         type Output = $name;
+
+         fn bitor (self, rhs: &'b $name) -> $name {
+            let mut result = $name::blank();
+            for (d, a, b) in itertools::multizip((&mut result.raw, &self.raw, &rhs.raw)) {
+               *d = a|b;
+            }
+            result
+         }
+      }
+
+      impl BitOr for $name {
+         type Output = $name;
+
+         fn bitor (mut self, rhs: Self) -> $name {
+            for (a, b) in self.raw.iter_mut().zip(rhs.raw.iter()) {
+               *a |= *b;
+            }
+            self
+         }
+      }
+
+      impl<'a> Not for &'a $name {
+         type Output = $name;
+
+         fn not (self) -> $name {
+            let mut result = $name::blank();
+            for (d, a) in result.raw.iter_mut().zip(self.raw.iter()) {
+               *d = !a;
+            }
+            result
+         }
+      }
+
+      impl Not for $name {
+         type Output = $name;
+
+         fn not (mut self) -> $name {
+            for a in self.raw.iter_mut() {
+               *a = !*a;
+            }
+            self
+         }
+      }
+
+      impl Index<usize> for $name {
+         type Output = u8;
+
+         fn index (&self, index: usize) -> &u8 {
+            &self.raw[index]
+         }
+      }
+
+      impl IndexMut<usize> for $name {
+         fn index_mut (&mut self, index: usize) -> &mut u8 {
+            &mut self.raw[index]
+         }
+      }
+
+      impl Deref for $name {
+         type Target = [u8; $bytes];
+
+         fn deref (&self) -> &[u8; $bytes] {
+            &self.raw
+         }
+      }
+
+      impl DerefMut for $name {
+         fn deref_mut (&mut self) -> &mut [u8; $bytes] {
+            &mut self.raw
+         }
+      }
+
+      impl Hash for $name {
+         /// Feeds the backing bytes to the hasher in a single write, so that `PlainHasher`
+         /// can lift the key's own prefix straight out without the length framing the slice
+         /// impl would otherwise prepend.
+         fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write(&self.raw);
+         }
+      }
    }
+}
 
+impl_subotai_hash!(SubotaiHash160, 20);
+impl_subotai_hash!(SubotaiHash256, 32);
+
+/// The default Subotai hash width, 160 bits of SHA-1, used for node IDs and storage keys.
+pub type SubotaiHash = SubotaiHash160;
+
+impl SubotaiHash160 {
    /// Generates a SHA-1 hash from a string.
-   pub fn sha1(data: &str) -> SubotaiHash {
+   pub fn sha1(data: &str) -> SubotaiHash160 {
       let mut m = sha1::Sha1::new();
       m.reset();
       m.update(data.as_bytes());
-      SubotaiHash {
+      SubotaiHash160 {
          raw: m.digest().bytes(),
       }
    }
 
-   /// Creates a random hash at a given XOR distance from another (height of their XOR value).
-   pub fn random_at_distance(reference: &SubotaiHash, distance: usize) -> SubotaiHash {
-      let mut random_hash = SubotaiHash::random();
-      let distance_ones = (&random_hash ^ reference).into_ones();
-      for index in distance_ones.rev() {
-         random_hash.flip_bit(index);
-         if let Some(height) = (&random_hash ^ reference).height() {
-            if height == distance {
-               return random_hash;
-            } else if height < distance {
-               random_hash.flip_bit(distance);
-               return random_hash;
-            }
-         }
-      }
-       
-      random_hash
+   /// Generates a SHA-1 hash from an arbitrary byte slice, for content addressing of
+   /// binary payloads that need not be valid UTF-8.
+   pub fn from_bytes(data: &[u8]) -> SubotaiHash160 {
+      let mut builder = SubotaiHashBuilder::new();
+      builder.add(data);
+      builder.build()
    }
+}
 
-   /// Provides an iterator through the indices
-   /// of each of its "0" bits.
-   pub fn zeroes(&self) -> Zeroes {
-      Zeroes {
-         hash  : self,
-         index : 0,
-         rev   : HASH_SIZE
-      }
+/// Incremental builder for SHA-1 `SubotaiHash` keys. It lets a hash be derived from
+/// structured or streamed data by feeding successive byte slices, avoiding the
+/// allocation and concatenation that `SubotaiHash::sha1` forces on its callers.
+pub struct SubotaiHashBuilder {
+   state : sha1::Sha1,
+}
+
+impl SubotaiHashBuilder {
+   /// Creates an empty builder.
+   pub fn new() -> SubotaiHashBuilder {
+      let mut state = sha1::Sha1::new();
+      state.reset();
+      SubotaiHashBuilder { state }
    }
 
-   /// Provides an iterator through the indices
-   /// of each of its "1" bits.
-   pub fn ones(&self) -> Ones {
-      Ones {
-         hash  : self,
-         index : 0,
-         rev   : HASH_SIZE
-      }
+   /// Feeds another slice of bytes into the running digest.
+   pub fn add(&mut self, bytes: &[u8]) -> &mut SubotaiHashBuilder {
+      self.state.update(bytes);
+      self
    }
 
-   /// Provides a consuming iterator through the 
-   /// indices of each of its "0" bits.
-   pub fn into_zeroes(self) -> IntoZeroes {
-      IntoZeroes {
-         hash  : self,
-         index : 0,
-         rev   : HASH_SIZE
+   /// Consumes the builder and produces the resulting hash.
+   pub fn build(self) -> SubotaiHash {
+      SubotaiHash {
+         raw: self.state.digest().bytes(),
       }
    }
+}
 
-   /// Provides a consuming iterator through the 
-   /// indices of each of its "1" bits.
-   pub fn into_ones(self) -> IntoOnes {
-      IntoOnes {
-         hash  : self,
-         index : 0,
-         rev   : HASH_SIZE
-      }
+impl Default for SubotaiHashBuilder {
+   fn default() -> SubotaiHashBuilder {
+      SubotaiHashBuilder::new()
    }
+}
 
-   /// Computes the bit index of the highest "1". Returns None for a blank hash.
-   pub fn height(&self) -> Option<usize> {
-      let last_nonzero_byte = self.raw.iter().enumerate().rev().find(|&pair| *pair.1 != 0);
-      if let Some((index, byte)) = last_nonzero_byte {
-         for bit in (0..8).rev() {
-            if (byte & (1 << bit)) != 0 {
-               return Some((8 * index + bit) as usize)
-            }
+/// A `HashMap` keyed by `SubotaiHash` through the zero-cost `PlainHasher`, suitable for
+/// the routing table and storage index where the keys are already uniformly distributed.
+pub type SubotaiMap<V> = HashMap<SubotaiHash, V, BuildHasherDefault<PlainHasher>>;
+
+/// Identity `Hasher` for `SubotaiHash` keys. A `SubotaiHash` already wraps 160 bits of
+/// uniformly distributed data, so running SipHash over it is pure overhead; this takes the
+/// first 8 bytes of the key as the `u64` hash instead.
+#[derive(Default)]
+pub struct PlainHasher {
+   prefix   : u64,
+   consumed : usize,
+}
+
+impl Hasher for PlainHasher {
+   fn finish(&self) -> u64 {
+      self.prefix
+   }
+
+   fn write(&mut self, bytes: &[u8]) {
+      // Take the first 8 bytes seen across any number of writes and ignore the rest, so a
+      // multi-chunk write pattern yields the same prefix as a single 8-plus-byte write.
+      for byte in bytes {
+         if self.consumed >= 8 {
+            break;
          }
+         self.prefix |= (*byte as u64) << (8 * self.consumed);
+         self.consumed += 1;
       }
-      None
    }
+}
 
-   /// Flips a bit in the hash.
-   pub fn flip_bit(&mut self, position : usize) {
-      if position >= HASH_SIZE { return; }
-      let byte = &mut self.raw[position / 8];
-      *byte ^= 1 << (position % 8);
-   }
+/// Error produced when a `SubotaiHash` cannot be parsed from its textual representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseHashError {
+   /// The string held more hex nibbles than the target hash width can store.
+   TooLong { max_nibbles: usize },
+   /// The string held a character that is not a hexadecimal digit.
+   InvalidDigit(char),
 }
 
-impl fmt::Display for SubotaiHash {
+impl fmt::Display for ParseHashError {
    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-      let mut leftpad_over = false;
-      let mut hex = String::new();
-      hex.push_str("0x[");
-      for byte in self.raw.iter().rev() {
-         if *byte > 0u8 {
-            leftpad_over = true;
-         }
+      match *self {
+         ParseHashError::TooLong { max_nibbles } =>
+            write!(f, "the hash text is longer than {} nibbles", max_nibbles),
+         ParseHashError::InvalidDigit(digit) =>
+            write!(f, "'{}' is not a hexadecimal digit", digit),
+      }
+   }
+}
 
-         if leftpad_over {
-            write!(&mut hex, "{:01$X}", byte, 2).unwrap();
-         }
+impl Error for ParseHashError {
+   fn description(&self) -> &str {
+      match *self {
+         ParseHashError::TooLong { .. } => "hash text too long",
+         ParseHashError::InvalidDigit(_) => "invalid hexadecimal digit",
       }
-      hex.push_str("]");
-      write!(f, "{}", hex)
    }
 }
 
 /// Iterator through the indices of each '0' in a hash.
-pub struct Zeroes<'a> { 
-   hash  : &'a SubotaiHash,
+pub struct Zeroes<'a> {
+   raw   : &'a [u8],
    index : usize,
    rev   : usize
 }
 
 /// Iterator through the indices of each '1' in a hash.
-pub struct Ones<'a> { 
-   hash  : &'a SubotaiHash,
+pub struct Ones<'a> {
+   raw   : &'a [u8],
    index : usize,
    rev   : usize,
 }
 
 /// Consuming iterator through the indices of each '0' in a hash.
-pub struct IntoZeroes { 
-   hash  : SubotaiHash,
+pub struct IntoZeroes {
+   raw   : Vec<u8>,
    index : usize,
    rev   : usize
 }
 
 /// Consuming iterator through the indices of each '1' in a hash.
-pub struct IntoOnes { 
-   hash  : SubotaiHash,
+pub struct IntoOnes {
+   raw   : Vec<u8>,
    index : usize,
    rev   : usize,
 }
@@ -179,7 +516,7 @@ impl<'a> Iterator for Zeroes<'a> {
 
    fn next(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_index = self.hash.raw[self.index / 8] & (1 << (self.index % 8));
+         let value_at_index = self.raw[self.index / 8] & (1 << (self.index % 8));
          self.index += 1;
          if value_at_index == 0 {
             return Some(self.index - 1);
@@ -194,7 +531,7 @@ impl<'a> Iterator for Ones<'a> {
 
    fn next(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_index = self.hash.raw[self.index / 8] & (1 << (self.index % 8));
+         let value_at_index = self.raw[self.index / 8] & (1 << (self.index % 8));
          self.index += 1;
          if value_at_index != 0 {
             return Some(self.index - 1);
@@ -207,7 +544,7 @@ impl<'a> Iterator for Ones<'a> {
 impl<'a> DoubleEndedIterator for Zeroes<'a> {
    fn next_back(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_rev = self.hash.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
+         let value_at_rev = self.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
          self.rev -= 1;
          if value_at_rev == 0 {
             return Some(self.rev);
@@ -220,7 +557,7 @@ impl<'a> DoubleEndedIterator for Zeroes<'a> {
 impl<'a> DoubleEndedIterator for Ones<'a> {
    fn next_back(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_rev = self.hash.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
+         let value_at_rev = self.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
          self.rev -= 1;
          if value_at_rev != 0 {
             return Some(self.rev);
@@ -235,7 +572,7 @@ impl Iterator for IntoZeroes {
 
    fn next(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_index = self.hash.raw[self.index / 8] & (1 << (self.index % 8));
+         let value_at_index = self.raw[self.index / 8] & (1 << (self.index % 8));
          self.index += 1;
          if value_at_index == 0 {
             return Some(self.index - 1);
@@ -250,7 +587,7 @@ impl Iterator for IntoOnes {
 
    fn next(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_index = self.hash.raw[self.index / 8] & (1 << (self.index % 8));
+         let value_at_index = self.raw[self.index / 8] & (1 << (self.index % 8));
          self.index += 1;
          if value_at_index != 0 {
             return Some(self.index - 1);
@@ -263,7 +600,7 @@ impl Iterator for IntoOnes {
 impl DoubleEndedIterator for IntoZeroes {
    fn next_back(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_rev = self.hash.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
+         let value_at_rev = self.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
          self.rev -= 1;
          if value_at_rev == 0 {
             return Some(self.rev);
@@ -276,7 +613,7 @@ impl DoubleEndedIterator for IntoZeroes {
 impl DoubleEndedIterator for IntoOnes {
    fn next_back(&mut self) -> Option<usize> {
       while self.index < self.rev {
-         let value_at_rev = self.hash.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
+         let value_at_rev = self.raw[(self.rev-1) / 8] & (1 << ((self.rev-1) % 8));
          self.rev -= 1;
          if value_at_rev != 0 {
             return Some(self.rev);
@@ -286,51 +623,6 @@ impl DoubleEndedIterator for IntoOnes {
    }
 }
 
-impl PartialOrd for SubotaiHash {
-   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-      for (a,b) in self.raw.iter().rev().zip(other.raw.iter().rev()) {
-         match a.cmp(b) {
-            Ordering::Less => return Some(Ordering::Less),
-            Ordering::Greater => return Some(Ordering::Greater),
-            Ordering::Equal => ()
-         }
-      }
-      None 
-   }
-}
-
-impl Ord for SubotaiHash {
-   fn cmp(&self, other: &Self) -> Ordering {
-      match self.partial_cmp(other) {
-         Some(order) => order,
-         None => Ordering::Equal
-      }
-   }
-}
-
-impl<'a, 'b> BitXor<&'b SubotaiHash> for &'a SubotaiHash {
-   type Output = SubotaiHash;
-
-   fn bitxor (self, rhs: &'b SubotaiHash) -> SubotaiHash {
-      let mut result = SubotaiHash::blank();
-      for (d, a, b) in itertools::multizip((&mut result.raw, &self.raw, &rhs.raw)) {
-         *d = a^b;
-      }
-      result
-   }
-}
-
-impl BitXor for SubotaiHash {
-   type Output = SubotaiHash;
-
-   fn bitxor (mut self, rhs: Self) -> SubotaiHash {
-      for (a, b) in self.raw.iter_mut().zip(rhs.raw.iter()) {
-         *a ^= *b;
-      }
-      self
-   }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -360,7 +652,7 @@ mod tests {
     fn computing_height() {
        let mut test_hash = SubotaiHash::blank();
        assert!(test_hash.height().is_none());
-       
+
        // First bit
        test_hash.raw[0] = 1;
        assert_eq!(test_hash.height(), Some(0));
@@ -397,6 +689,107 @@ mod tests {
        }
     }
 
+   #[test]
+   fn hex_round_trip() {
+      let values = vec![
+         SubotaiHash::blank(),
+         SubotaiHash::random(),
+         SubotaiHash::sha1("subotai"),
+      ];
+
+      for hash in values {
+         assert_eq!(hash.to_string().parse::<SubotaiHash>().unwrap(), hash);
+      }
+   }
+
+   #[test]
+   fn parsing_tolerates_missing_leading_zeroes() {
+      let mut expected = SubotaiHash::blank();
+      expected.raw[0] = 0x0a;
+      assert_eq!("0x[A]".parse::<SubotaiHash>().unwrap(), expected);
+      assert_eq!("0x[0A]".parse::<SubotaiHash>().unwrap(), expected);
+   }
+
+   #[test]
+   fn parsing_rejects_overlong_and_invalid_input() {
+      let overlong: String = std::iter::repeat('f').take(HASH_SIZE_BYTES * 2 + 1).collect();
+      assert_eq!(
+         overlong.parse::<SubotaiHash>(),
+         Err(ParseHashError::TooLong { max_nibbles: HASH_SIZE_BYTES * 2 })
+      );
+      assert_eq!("0x[0g]".parse::<SubotaiHash>(), Err(ParseHashError::InvalidDigit('g')));
+   }
+
+   #[test]
+   fn incremental_builder_matches_single_shot() {
+      let mut builder = SubotaiHashBuilder::new();
+      builder.add(b"subota").add(b"i");
+      assert_eq!(builder.build(), SubotaiHash::sha1("subotai"));
+      assert_eq!(SubotaiHash::from_bytes(b"subotai"), SubotaiHash::sha1("subotai"));
+   }
+
+   #[test]
+   fn bitwise_operators() {
+      let ones = !&SubotaiHash::blank();
+      assert_eq!(ones[0], 0xff);
+      assert_eq!((&ones & &SubotaiHash::blank()), SubotaiHash::blank());
+      assert_eq!((&SubotaiHash::blank() | &ones), ones);
+
+      let mut hash = SubotaiHash::blank();
+      hash[1] = 0x80;
+      assert_eq!(hash.raw[1], 0x80);
+      assert_eq!(hash.len(), HASH_SIZE_BYTES);
+   }
+
+   #[test]
+   fn plain_hasher_keyed_map() {
+      let mut map: SubotaiMap<usize> = SubotaiMap::default();
+      let key = SubotaiHash::sha1("subotai");
+      map.insert(key.clone(), 42);
+      assert_eq!(map.get(&key), Some(&42));
+      assert_eq!(map.get(&SubotaiHash::blank()), None);
+   }
+
+   #[test]
+   fn wider_variant_shares_core_logic() {
+      let mut wide = SubotaiHash256::blank();
+      assert!(wide.height().is_none());
+      wide.flip_bit(255);
+      assert_eq!(wide.height(), Some(255));
+      assert_eq!(&wide ^ &wide, SubotaiHash256::blank());
+      assert_eq!(wide.raw.len(), 32);
+
+      let keyed = SubotaiHash256::random_with_seed(11);
+      assert_eq!(keyed.to_string().parse::<SubotaiHash256>().unwrap(), keyed);
+
+      let overlong: String = std::iter::repeat('f').take(65).collect();
+      assert_eq!(
+         SubotaiHash256::from_hex(&overlong),
+         Err(ParseHashError::TooLong { max_nibbles: 64 })
+      );
+
+      // The wider type is a first-class key and carries the full operator surface.
+      assert!(keyed.partial_cmp(&keyed) == Some(Ordering::Equal));
+      assert_eq!((!&SubotaiHash256::blank())[0], 0xff);
+      let mut map: HashMap<SubotaiHash256, usize, BuildHasherDefault<PlainHasher>> =
+         HashMap::default();
+      map.insert(keyed.clone(), 7);
+      assert_eq!(map.get(&keyed), Some(&7));
+   }
+
+   #[test]
+   fn seeded_generation_is_reproducible() {
+      assert_eq!(SubotaiHash::random_with_seed(42), SubotaiHash::random_with_seed(42));
+
+      let reference = SubotaiHash::random_with_seed(7);
+      let mut first = StdRng::from_seed(&[99]);
+      let mut second = StdRng::from_seed(&[99]);
+      assert_eq!(
+         SubotaiHash::random_at_distance_with(&mut first, &reference, 30),
+         SubotaiHash::random_at_distance_with(&mut second, &reference, 30)
+      );
+   }
+
    #[test]
    fn random_at_a_distance() {
       let test_hash = SubotaiHash::random();